@@ -0,0 +1,407 @@
+//! Turns the flat `Token` stream from the scanner into a tree of `Node`s
+//! that the renderer walks to produce Gleam source.
+
+use crate::scanner::{Range, Token};
+use std::iter::Peekable;
+use std::slice::Iter;
+
+type TokenIter<'a> = Peekable<Iter<'a, Token>>;
+
+#[derive(Debug, Clone)]
+pub enum FilterArg {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+// One `| name` or `| name(args)` step in an identifier's pipeline. A dotted
+// name (e.g. `my_mod.cap`) calls straight through to the imported module;
+// an undotted name resolves against the built-in `matcha_filters` module.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub name: String,
+    pub args: Vec<FilterArg>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Text(String),
+    Identifier(String, Vec<Filter>),
+    RawIdentifier(String),
+    Builder(String),
+    Import(String),
+    With((String, Range), String),
+    DisableEscaping,
+    // Ordered `if`/`elseif` branches, each its own (condition, body), plus an
+    // optional trailing `else` body.
+    If(Vec<(String, Vec<Node>)>, Option<Vec<Node>>),
+    // The loop body, plus an optional `else` body rendered when the list is empty.
+    For(String, Option<String>, String, Vec<Node>, Option<Vec<Node>>),
+}
+
+#[derive(Debug)]
+pub enum ParserError {
+    UnexpectedToken(String, Range),
+    UnexpectedEndOfInput,
+}
+
+pub fn parse(iter: &mut TokenIter) -> Result<Vec<Node>, ParserError> {
+    parse_until(iter, &[], false)
+}
+
+// `terminators` holds the statement keywords (e.g. "endif", "else") that should
+// stop this call without being consumed, handing control back to the caller
+// that knows how to handle them. `pending_trim_right` carries a `~` from the
+// tag immediately preceding this list (e.g. `{%~ if x ~%}`'s own trailing
+// `~`), so it strips the leading whitespace of this list's first Text node.
+fn parse_until(
+    iter: &mut TokenIter,
+    terminators: &[&str],
+    mut pending_trim_right: bool,
+) -> Result<Vec<Node>, ParserError> {
+    let mut nodes = vec![];
+
+    while let Some(token) = iter.peek() {
+        match token {
+            Token::Text(text) => {
+                iter.next();
+                let text = if pending_trim_right {
+                    text.trim_start().to_string()
+                } else {
+                    text.clone()
+                };
+                pending_trim_right = false;
+                nodes.push(Node::Text(text));
+            }
+            Token::Identifier(content, range, trim) => {
+                let content = content.clone();
+                let range = range.clone();
+                let trim = *trim;
+                iter.next();
+                if trim.left {
+                    apply_trim_left(&mut nodes);
+                }
+                let (name, filters) = parse_identifier_pipeline(&content, &range)?;
+                nodes.push(Node::Identifier(name, filters));
+                pending_trim_right = trim.right;
+            }
+            Token::RawIdentifier(name, _, trim) => {
+                let trim = *trim;
+                iter.next();
+                if trim.left {
+                    apply_trim_left(&mut nodes);
+                }
+                nodes.push(Node::RawIdentifier(name.clone()));
+                pending_trim_right = trim.right;
+            }
+            Token::Builder(name, _, trim) => {
+                let trim = *trim;
+                iter.next();
+                if trim.left {
+                    apply_trim_left(&mut nodes);
+                }
+                nodes.push(Node::Builder(name.clone()));
+                pending_trim_right = trim.right;
+            }
+            Token::Pragma(content, range) => {
+                let content = content.clone();
+                let range = range.clone();
+                iter.next();
+                pending_trim_right = false;
+                parse_pragma(&content, &range, &mut nodes)?;
+            }
+            Token::Statement(content, range, trim) => {
+                let keyword = content.split_whitespace().next().unwrap_or("");
+                if terminators.contains(&keyword) {
+                    break;
+                }
+
+                let content = content.clone();
+                let range = range.clone();
+                let trim = *trim;
+                iter.next();
+
+                if trim.left {
+                    apply_trim_left(&mut nodes);
+                }
+
+                let node = match keyword {
+                    "if" => parse_if(&content, &range, iter, trim.right)?,
+                    "for" => parse_for(&content, &range, iter, trim.right)?,
+                    _ => return Err(ParserError::UnexpectedToken(content, range)),
+                };
+                nodes.push(node.0);
+                pending_trim_right = node.1;
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+// Strips trailing whitespace from the last node if it is Text — used when a
+// `~`-prefixed tag immediately follows it in the same sibling list.
+fn apply_trim_left(nodes: &mut [Node]) {
+    if let Some(Node::Text(text)) = nodes.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+}
+
+// Parses `name | filter | filter(args)` into the base identifier name and
+// its ordered filter pipeline.
+fn parse_identifier_pipeline(
+    content: &str,
+    range: &Range,
+) -> Result<(String, Vec<Filter>), ParserError> {
+    let mut segments = split_outside_quotes(content, '|').into_iter();
+    let name = segments.next().unwrap_or_default().trim().to_string();
+    let filters = segments
+        .map(|segment| parse_filter(segment.trim(), range))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((name, filters))
+}
+
+fn parse_filter(segment: &str, range: &Range) -> Result<Filter, ParserError> {
+    match segment.find('(') {
+        None => Ok(Filter {
+            name: segment.to_string(),
+            args: vec![],
+        }),
+        Some(open_idx) => {
+            let name = segment[..open_idx].trim().to_string();
+            let inner = segment[open_idx + 1..]
+                .strip_suffix(')')
+                .ok_or_else(|| ParserError::UnexpectedToken(segment.to_string(), range.clone()))?;
+            let args = split_filter_args(inner)
+                .into_iter()
+                .map(|raw| parse_filter_arg(&raw, range))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Filter { name, args })
+        }
+    }
+}
+
+// Splits on commas outside of double-quoted string literals.
+fn split_filter_args(input: &str) -> Vec<String> {
+    let mut args = split_outside_quotes(input, ',');
+    // Drop the trailing dangling segment so a zero-arg call like `truncate()`
+    // yields no args instead of one blank one.
+    if matches!(args.last(), Some(last) if last.trim().is_empty()) {
+        args.pop();
+    }
+    args.iter().map(|arg| arg.trim().to_string()).collect()
+}
+
+// Splits `input` on `delim`, skipping over any delimiter found inside a
+// double-quoted string literal so e.g. `default("a|b")` isn't torn in half.
+fn split_outside_quotes(input: &str, delim: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c == delim && !in_quotes => {
+                parts.push(current);
+                current = String::new();
+            }
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    parts
+}
+
+fn parse_filter_arg(raw: &str, range: &Range) -> Result<FilterArg, ParserError> {
+    let raw = raw.trim();
+    match raw {
+        "true" => Ok(FilterArg::Bool(true)),
+        "false" => Ok(FilterArg::Bool(false)),
+        _ => {
+            if let Some(inner) = raw.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                return Ok(FilterArg::String(inner.to_string()));
+            }
+            raw.parse::<i64>()
+                .map(FilterArg::Int)
+                .map_err(|_| ParserError::UnexpectedToken(raw.to_string(), range.clone()))
+        }
+    }
+}
+
+fn parse_pragma(content: &str, range: &Range, nodes: &mut Vec<Node>) -> Result<(), ParserError> {
+    let mut parts = content.splitn(2, char::is_whitespace);
+    match parts.next() {
+        Some("import") => {
+            let rest = parts.next().unwrap_or("").trim().to_string();
+            nodes.push(Node::Import(rest));
+            Ok(())
+        }
+        Some("with") => {
+            let rest = parts.next().unwrap_or("").trim();
+            let mut pieces = rest.splitn(3, char::is_whitespace);
+            let identifier = pieces.next().unwrap_or("").to_string();
+            let _as_keyword = pieces.next();
+            let type_name = pieces.next().unwrap_or("").trim().to_string();
+            nodes.push(Node::With((identifier, range.clone()), type_name));
+            Ok(())
+        }
+        Some("escape") if parts.next().unwrap_or("").trim() == "none" => {
+            nodes.push(Node::DisableEscaping);
+            Ok(())
+        }
+        _ => Err(ParserError::UnexpectedToken(
+            content.to_string(),
+            range.clone(),
+        )),
+    }
+}
+
+// Returns the constructed node alongside the trailing tag's trim-right flag,
+// so the caller can carry it forward as the next sibling's pending trim.
+fn parse_if(
+    content: &str,
+    range: &Range,
+    iter: &mut TokenIter,
+    incoming_trim_right: bool,
+) -> Result<(Node, bool), ParserError> {
+    let mut condition = content.strip_prefix("if").unwrap_or("").trim().to_string();
+    if condition.is_empty() {
+        return Err(ParserError::UnexpectedToken(
+            content.to_string(),
+            range.clone(),
+        ));
+    }
+
+    let mut branches = vec![];
+    let mut pending_trim_right = incoming_trim_right;
+
+    loop {
+        let body = parse_until(iter, &["elseif", "else", "endif"], pending_trim_right)?;
+        branches.push((condition.clone(), body));
+
+        let is_elseif = matches!(
+            iter.peek(),
+            Some(Token::Statement(content, _, _))
+                if content.split_whitespace().next() == Some("elseif")
+        );
+        if !is_elseif {
+            break;
+        }
+
+        match iter.next() {
+            Some(Token::Statement(content, elseif_range, trim)) => {
+                if trim.left {
+                    apply_trim_left(&mut branches.last_mut().unwrap().1);
+                }
+                condition = content
+                    .strip_prefix("elseif")
+                    .unwrap_or("")
+                    .trim()
+                    .to_string();
+                if condition.is_empty() {
+                    return Err(ParserError::UnexpectedToken(
+                        content.clone(),
+                        elseif_range.clone(),
+                    ));
+                }
+                pending_trim_right = trim.right;
+            }
+            _ => unreachable!("is_elseif only true for a Token::Statement"),
+        }
+    }
+
+    let mut else_nodes = None;
+    if let Some(Token::Statement(content, _, trim)) = iter.peek() {
+        if content.trim() == "else" {
+            let trim = *trim;
+            iter.next();
+            if trim.left {
+                apply_trim_left(&mut branches.last_mut().unwrap().1);
+            }
+            else_nodes = Some(parse_until(iter, &["endif"], trim.right)?);
+        }
+    }
+
+    match iter.next() {
+        Some(Token::Statement(content, _, trim)) if content.trim() == "endif" => {
+            if trim.left {
+                match &mut else_nodes {
+                    Some(nodes) => apply_trim_left(nodes),
+                    None => apply_trim_left(&mut branches.last_mut().unwrap().1),
+                }
+            }
+            Ok((Node::If(branches, else_nodes), trim.right))
+        }
+        Some(Token::Statement(content, range, _)) => {
+            Err(ParserError::UnexpectedToken(content.clone(), range.clone()))
+        }
+        _ => Err(ParserError::UnexpectedEndOfInput),
+    }
+}
+
+fn parse_for(
+    content: &str,
+    range: &Range,
+    iter: &mut TokenIter,
+    incoming_trim_right: bool,
+) -> Result<(Node, bool), ParserError> {
+    let rest = content.strip_prefix("for").unwrap_or("").trim();
+    let in_idx = rest
+        .find(" in ")
+        .ok_or_else(|| ParserError::UnexpectedToken(content.to_string(), range.clone()))?;
+
+    let head = rest[..in_idx].trim();
+    let list_identifier = rest[in_idx + 4..].trim().to_string();
+
+    let (entry_identifier, entry_type) = match head.find(" as ") {
+        Some(as_idx) => (
+            head[..as_idx].trim().to_string(),
+            Some(head[as_idx + 4..].trim().to_string()),
+        ),
+        None => (head.to_string(), None),
+    };
+
+    let mut loop_nodes = parse_until(iter, &["else", "endfor"], incoming_trim_right)?;
+
+    let mut else_nodes = None;
+    if let Some(Token::Statement(content, _, trim)) = iter.peek() {
+        if content.trim() == "else" {
+            let trim = *trim;
+            iter.next();
+            if trim.left {
+                apply_trim_left(&mut loop_nodes);
+            }
+            else_nodes = Some(parse_until(iter, &["endfor"], trim.right)?);
+        }
+    }
+
+    match iter.next() {
+        Some(Token::Statement(content, _, trim)) if content.trim() == "endfor" => {
+            if trim.left {
+                match &mut else_nodes {
+                    Some(nodes) => apply_trim_left(nodes),
+                    None => apply_trim_left(&mut loop_nodes),
+                }
+            }
+            Ok((
+                Node::For(
+                    entry_identifier,
+                    entry_type,
+                    list_identifier,
+                    loop_nodes,
+                    else_nodes,
+                ),
+                trim.right,
+            ))
+        }
+        Some(Token::Statement(content, range, _)) => {
+            Err(ParserError::UnexpectedToken(content.clone(), range.clone()))
+        }
+        _ => Err(ParserError::UnexpectedEndOfInput),
+    }
+}
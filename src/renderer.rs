@@ -1,4 +1,4 @@
-use crate::parser::Node;
+use crate::parser::{Filter, FilterArg, Node};
 use crate::scanner::Range;
 
 type NodeIter<'a> = std::iter::Peekable<std::slice::Iter<'a, Node>>;
@@ -9,7 +9,19 @@ pub enum RenderError {
 }
 
 pub fn render(iter: &mut NodeIter) -> Result<String, RenderError> {
-    let (builder_lines, imports, typed_params) = render_lines(iter)?;
+    let escape_disabled = contains_disable_escaping(iter.clone());
+    let (builder_lines, mut imports, typed_params) = render_lines(iter, escape_disabled)?;
+
+    if builder_lines.contains("matcha_runtime.escape_html(")
+        && !imports.iter().any(|details| details == "matcha_runtime")
+    {
+        imports.push("matcha_runtime".to_string());
+    }
+    if builder_lines.contains("matcha_filters.")
+        && !imports.iter().any(|details| details == "matcha_filters")
+    {
+        imports.push("matcha_filters".to_string());
+    }
 
     let import_lines = imports
         .iter()
@@ -51,9 +63,107 @@ pub fn render({}) -> String {{
     Ok(output)
 }
 
+// Compiles one pipeline step into a Gleam call wrapping `expr`. A dotted
+// filter name (e.g. `my_mod.cap`) is called as-is, relying on the user's own
+// `{> import}`; an undotted name resolves against `matcha_filters`.
+fn apply_filter(expr: &str, filter: &Filter) -> String {
+    let call_target = if filter.name.contains('.') {
+        filter.name.clone()
+    } else {
+        format!("matcha_filters.{}", filter.name)
+    };
+
+    let mut args = vec![expr.to_string()];
+    args.extend(filter.args.iter().map(render_filter_arg));
+
+    format!("{}({})", call_target, args.join(", "))
+}
+
+fn render_filter_arg(arg: &FilterArg) -> String {
+    match arg {
+        FilterArg::String(value) => format!("\"{}\"", value.replace('"', "\\\"")),
+        FilterArg::Int(value) => value.to_string(),
+        FilterArg::Bool(value) => if *value { "True" } else { "False" }.to_string(),
+    }
+}
+
+// Compiles an ordered if/elseif chain plus optional trailing else into a
+// single expression of nested `case` blocks, evaluated outermost-first so
+// only the first matching branch contributes to the builder.
+fn render_if_chain(
+    branches: &[(String, Vec<Node>)],
+    else_nodes: Option<&Vec<Node>>,
+    escape_disabled: bool,
+) -> Result<String, RenderError> {
+    match branches.split_first() {
+        None => match else_nodes {
+            Some(nodes) => {
+                let (lines, _, _) = render_lines(&mut nodes.iter().peekable(), escape_disabled)?;
+                Ok(format!(
+                    r#"{{
+            {}
+            builder
+}}"#,
+                    lines
+                ))
+            }
+            None => Ok("builder".to_string()),
+        },
+        Some(((condition, body), rest)) => {
+            let (body_lines, _, _) = render_lines(&mut body.iter().peekable(), escape_disabled)?;
+            let else_branch = render_if_chain(rest, else_nodes, escape_disabled)?;
+            Ok(format!(
+                r#"case {} {{
+        True -> {{
+            {}
+            builder
+        }}
+        False -> {}
+}}"#,
+                condition, body_lines, else_branch
+            ))
+        }
+    }
+}
+
+// `{> escape none}` disables escaping for the whole template regardless of
+// where the pragma appears, the same as `{> with}`/`{> import}` are resolved
+// independent of position. Walk the full tree (including `if`/`for` bodies)
+// up front so `render_lines` can seed every call, including ones for
+// identifiers that textually precede the pragma, with the right flag.
+fn contains_disable_escaping<'a>(nodes: impl Iterator<Item = &'a Node>) -> bool {
+    for node in nodes {
+        let found = match node {
+            Node::DisableEscaping => true,
+            Node::If(branches, else_nodes) => {
+                branches
+                    .iter()
+                    .any(|(_, body)| contains_disable_escaping(body.iter()))
+                    || else_nodes
+                        .as_ref()
+                        .is_some_and(|body| contains_disable_escaping(body.iter()))
+            }
+            Node::For(_, _, _, loop_nodes, else_nodes) => {
+                contains_disable_escaping(loop_nodes.iter())
+                    || else_nodes
+                        .as_ref()
+                        .is_some_and(|body| contains_disable_escaping(body.iter()))
+            }
+            _ => false,
+        };
+        if found {
+            return true;
+        }
+    }
+    false
+}
+
 type RenderDetails = (String, Vec<String>, Vec<(String, String)>);
 
-fn render_lines(iter: &mut NodeIter) -> Result<RenderDetails, RenderError> {
+fn render_lines(
+    iter: &mut NodeIter,
+    mut escape_disabled: bool,
+) -> Result<RenderDetails, RenderError> {
     let mut builder_lines = String::new();
     let mut imports = vec![];
 
@@ -71,13 +181,34 @@ fn render_lines(iter: &mut NodeIter) -> Result<RenderDetails, RenderError> {
                     text.replace("\"", "\\\"")
                 ));
             }
-            Some(Node::Identifier(name)) => {
+            Some(Node::Identifier(name, filters)) => {
+                iter.next();
+                let expr = filters
+                    .iter()
+                    .fold(name.clone(), |expr, filter| apply_filter(&expr, filter));
+                if escape_disabled {
+                    builder_lines.push_str(&format!(
+                        "    let builder = string_builder.append(builder, {})\n",
+                        expr
+                    ));
+                } else {
+                    builder_lines.push_str(&format!(
+                        "    let builder = string_builder.append(builder, matcha_runtime.escape_html({}))\n",
+                        expr
+                    ));
+                }
+            }
+            Some(Node::RawIdentifier(name)) => {
                 iter.next();
                 builder_lines.push_str(&format!(
                     "    let builder = string_builder.append(builder, {})\n",
                     name
                 ));
             }
+            Some(Node::DisableEscaping) => {
+                iter.next();
+                escape_disabled = true;
+            }
             Some(Node::Builder(name)) => {
                 iter.next();
                 builder_lines.push_str(&format!(
@@ -101,26 +232,18 @@ fn render_lines(iter: &mut NodeIter) -> Result<RenderDetails, RenderError> {
 
                 typed_params.push((identifier.clone(), type_.clone()));
             }
-            Some(Node::If(identifier_name, if_nodes, else_nodes)) => {
+            Some(Node::If(branches, else_nodes)) => {
                 iter.next();
-                let (if_lines, _, _) = render_lines(&mut if_nodes.iter().peekable())?;
-                let (else_lines, _, _) = render_lines(&mut else_nodes.iter().peekable())?;
-                builder_lines.push_str(&format!(
-                    r#"    let builder = case {} {{
-        True -> {{
-            {}
-            builder
-        }}
-        False -> {{
-            {}
-            builder
-        }}
-}}
-"#,
-                    identifier_name, if_lines, else_lines
-                ));
+                let case_expr = render_if_chain(branches, else_nodes.as_ref(), escape_disabled)?;
+                builder_lines.push_str(&format!("    let builder = {}\n", case_expr));
             }
-            Some(Node::For(entry_identifier, entry_type, list_identifier, loop_nodes)) => {
+            Some(Node::For(
+                entry_identifier,
+                entry_type,
+                list_identifier,
+                loop_nodes,
+                else_nodes,
+            )) => {
                 iter.next();
 
                 let entry_type = entry_type
@@ -128,15 +251,36 @@ fn render_lines(iter: &mut NodeIter) -> Result<RenderDetails, RenderError> {
                     .map(|value| format!(": {}", value))
                     .unwrap_or_else(|| "".to_string());
 
-                let (loop_lines, _, _) = render_lines(&mut loop_nodes.iter().peekable())?;
-                builder_lines.push_str(&format!(
-                    r#"    let builder = list.fold({}, builder, fn(builder, {}{}) {{
+                let (loop_lines, _, _) =
+                    render_lines(&mut loop_nodes.iter().peekable(), escape_disabled)?;
+                let fold_expr = format!(
+                    r#"list.fold({}, builder, fn(builder, {}{}) {{
         {}
         builder
-}})
-"#,
+}})"#,
                     list_identifier, entry_identifier, entry_type, loop_lines
-                ));
+                );
+
+                match else_nodes {
+                    Some(empty_nodes) => {
+                        let (empty_lines, _, _) =
+                            render_lines(&mut empty_nodes.iter().peekable(), escape_disabled)?;
+                        builder_lines.push_str(&format!(
+                            r#"    let builder = case list.is_empty({}) {{
+        True -> {{
+            {}
+            builder
+        }}
+        False -> {}
+}}
+"#,
+                            list_identifier, empty_lines, fold_expr
+                        ));
+                    }
+                    None => {
+                        builder_lines.push_str(&format!("    let builder = {}\n", fold_expr));
+                    }
+                }
             }
             None => break,
         }
@@ -313,4 +457,137 @@ Hello{% if user.is_admin %} Admin{% endif %}"
 Hello {[ name ]}, good to meet you"
         );
     }
+
+    #[test]
+    fn test_render_escaped_identifier() {
+        assert_render!(
+            "{> with name as String
+Hello {{ name }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_raw_identifier() {
+        assert_render!(
+            "{> with name as String
+Hello {{{ name }}}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_escape_none_pragma() {
+        assert_render!(
+            "{> escape none
+{> with name as String
+Hello {{ name }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_multiline_trimmed() {
+        assert_render!(
+            r#"{> with my_list as List(String)
+<ul>
+{%~ for entry in my_list ~%}
+    <li>{{ entry }}</li>
+{%~ endfor ~%}
+</ul>"#
+        );
+    }
+
+    #[test]
+    fn test_render_trim_expression_tag() {
+        assert_render!(
+            "{> with name as String
+Hello   {{~ name ~}}   , good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_single_filter() {
+        assert_render!(
+            "{> with name as String
+Hello {{ name | upper }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_filter_pipeline_with_args() {
+        assert_render!(
+            "{> with name as String
+Hello {{ name | upper | truncate(20) }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_filter_from_imported_module() {
+        assert_render!(
+            "{> import my_mod\n{> with name as String\nHello {{ name | my_mod.cap }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_filter_with_bool_arg() {
+        assert_render!(
+            "{> import my_mod\n{> with name as String\nHello {{ name | my_mod.flag(true, false) }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_filter_with_pipe_in_string_arg() {
+        assert_render!(
+            "{> with name as String\nHello {{ name | default(\"a|b\") }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_filter_with_close_tag_in_string_arg() {
+        assert_render!(
+            "{> with name as String\nHello {{ name | default(\"}}\") }}, good to meet you"
+        );
+    }
+
+    #[test]
+    fn test_render_elseif_chain() {
+        assert_render!(
+            "{> with is_admin as Bool
+{> with is_editor as Bool
+{> with is_member as Bool
+{% if is_admin %}Admin{% elseif is_editor %}Editor{% elseif is_member %}Member{% else %}Guest{% endif %}"
+        );
+    }
+
+    #[test]
+    fn test_render_elseif_without_trailing_else() {
+        assert_render!(
+            "{> with is_admin as Bool
+{> with is_editor as Bool
+{% if is_admin %}Admin{% elseif is_editor %}Editor{% endif %}"
+        );
+    }
+
+    #[test]
+    fn test_render_elseif_with_empty_condition_is_rejected() {
+        assert_render!(
+            "{> with is_admin as Bool
+{% if is_admin %}Admin{% elseif %}Guest{% endif %}"
+        );
+    }
+
+    #[test]
+    fn test_render_escape_none_pragma_applies_before_its_position() {
+        assert_render!(
+            "{> with name as String
+Hello {{ name }}, good to meet you
+{> escape none"
+        );
+    }
+
+    #[test]
+    fn test_render_for_with_empty_fallback() {
+        assert_render!(
+            "{> with list as List(String)
+{% for item in list %}{{ item }}{% else %}No items{% endfor %}"
+        );
+    }
 }
@@ -0,0 +1,143 @@
+//! Splits a template's source text into a flat stream of tokens: runs of
+//! plain text, and the tag kinds (`{{ }}`, `{{{ }}}`, `{[ ]}`, `{% %}`, `{> }`
+//! pragma lines). Expression and statement tags may carry `~` trim markers
+//! (e.g. `{%~ ... ~%}`) tracked via `Trim`. The parser is responsible for
+//! turning this flat stream into a tree of `Node`s.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    pub start: usize,
+    pub end: usize,
+}
+
+// Whether a tag carries a leading `~` (strip trailing whitespace from the
+// preceding Text) and/or a trailing `~` (strip leading whitespace from the
+// following Text). Only expression/statement tags support trim markers;
+// pragma lines don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trim {
+    pub left: bool,
+    pub right: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Text(String),
+    Identifier(String, Range, Trim),
+    RawIdentifier(String, Range, Trim),
+    Builder(String, Range, Trim),
+    Statement(String, Range, Trim),
+    Pragma(String, Range),
+}
+
+#[derive(Debug)]
+pub enum ScanError {
+    UnterminatedTag(Range),
+}
+
+// `{{{ }}}` is checked ahead of `{{ }}` since both share the same opening
+// character sequence and the longer one must win.
+const TAGS: [(&str, &str); 5] = [
+    ("{{{", "}}}"),
+    ("{{", "}}"),
+    ("{[", "]}"),
+    ("{%", "%}"),
+    ("{>", "\n"),
+];
+
+pub fn scan(input: &str) -> Result<Vec<Token>, ScanError> {
+    let mut tokens = vec![];
+    let mut rest = input;
+    let mut offset = 0;
+
+    loop {
+        match find_next_tag(rest) {
+            None => {
+                if !rest.is_empty() {
+                    tokens.push(Token::Text(rest.to_string()));
+                }
+                break;
+            }
+            Some((open_idx, open_tag, close_tag)) => {
+                if open_idx > 0 {
+                    tokens.push(Token::Text(rest[..open_idx].to_string()));
+                }
+
+                let after_open = &rest[open_idx + open_tag.len()..];
+                let close_idx = match find_unquoted(after_open, close_tag) {
+                    Some(idx) => idx,
+                    // A pragma line may be the last line of the template, with no
+                    // trailing newline to close it.
+                    None if close_tag == "\n" => after_open.len(),
+                    None => {
+                        return Err(ScanError::UnterminatedTag(Range {
+                            start: offset + open_idx,
+                            end: offset + open_idx + open_tag.len(),
+                        }))
+                    }
+                };
+
+                let mut trim = Trim::default();
+                let mut body = after_open[..close_idx].trim();
+                if open_tag != "{>" {
+                    if let Some(rest) = body.strip_prefix('~') {
+                        trim.left = true;
+                        body = rest.trim_start();
+                    }
+                    if let Some(rest) = body.strip_suffix('~') {
+                        trim.right = true;
+                        body = rest.trim_end();
+                    }
+                }
+                let content = body.to_string();
+
+                let consumed = open_idx + open_tag.len() + close_idx + close_tag.len();
+                let range = Range {
+                    start: offset + open_idx,
+                    end: offset + consumed.min(rest.len()),
+                };
+
+                let token = match open_tag {
+                    "{{{" => Token::RawIdentifier(content, range, trim),
+                    "{{" => Token::Identifier(content, range, trim),
+                    "{[" => Token::Builder(content, range, trim),
+                    "{%" => Token::Statement(content, range, trim),
+                    "{>" => Token::Pragma(content, range),
+                    _ => unreachable!(),
+                };
+                tokens.push(token);
+
+                let consumed = consumed.min(rest.len());
+                offset += consumed;
+                rest = &rest[consumed..];
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn find_next_tag(input: &str) -> Option<(usize, &'static str, &'static str)> {
+    TAGS.iter()
+        .filter_map(|(open, close)| input.find(open).map(|idx| (idx, *open, *close)))
+        .min_by_key(|(idx, _, _)| *idx)
+}
+
+// Finds the first occurrence of `needle` in `haystack` that isn't inside a
+// double-quoted string literal, so a filter argument like `default("}}")`
+// doesn't close the tag early.
+fn find_unquoted(haystack: &str, needle: &str) -> Option<usize> {
+    let mut in_quotes = false;
+
+    for (idx, c) in haystack.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            continue;
+        }
+        if !in_quotes && haystack[idx..].starts_with(needle) {
+            return Some(idx);
+        }
+    }
+
+    None
+}